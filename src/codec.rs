@@ -0,0 +1,355 @@
+use anyhow::bail;
+use bytes::{Buf, BufMut, BytesMut};
+use memchr::memchr;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::resp_protocol::RespValue;
+
+/// A `tokio_util::codec` implementation of the RESP wire protocol. `Framed` calls `decode`
+/// every time more bytes arrive off the socket; returning `Ok(None)` tells it the buffered
+/// bytes don't yet hold a complete frame, so it keeps them around and retries once more data
+/// lands. That's what lets a bulk string or array whose payload straddles two TCP packets
+/// parse correctly instead of erroring out partway through.
+#[derive(Debug, Default)]
+pub struct RespCodec;
+
+impl Decoder for RespCodec {
+    type Item = RespValue;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> anyhow::Result<Option<RespValue>> {
+        match parse(src)? {
+            Some((value, consumed)) => {
+                src.advance(consumed);
+                Ok(Some(value))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl Encoder<RespValue> for RespCodec {
+    type Error = anyhow::Error;
+
+    fn encode(&mut self, value: RespValue, dst: &mut BytesMut) -> anyhow::Result<()> {
+        write_value(&value, dst);
+        Ok(())
+    }
+}
+
+/// Tries to parse a single `RespValue` from the front of `buf` without consuming it, returning
+/// the value together with the number of bytes it spans. Returns `Ok(None)` if `buf` doesn't
+/// yet hold a complete frame.
+fn parse(buf: &[u8]) -> anyhow::Result<Option<(RespValue, usize)>> {
+    let Some(&magic) = buf.first() else {
+        return Ok(None);
+    };
+    let rest = &buf[1..];
+    let parsed = match magic {
+        b'+' => parse_line(rest)?.map(|(s, n)| (RespValue::SimpleString(s), n)),
+        b'-' => parse_line(rest)?.map(|(s, n)| (RespValue::Error(s), n)),
+        b':' => match parse_line(rest)? {
+            Some((s, n)) => Some((RespValue::Integer(s.parse()?), n)),
+            None => None,
+        },
+        b'$' => parse_bulk_string(rest)?,
+        b'*' => parse_array(rest)?,
+        magic => bail!("invalid magic byte: {}", magic),
+    };
+    Ok(parsed.map(|(value, consumed)| (value, consumed + 1)))
+}
+
+fn parse_bulk_string(buf: &[u8]) -> anyhow::Result<Option<(RespValue, usize)>> {
+    let Some((len, mut consumed)) = parse_length(buf)? else {
+        return Ok(None);
+    };
+    let Some(len) = len else {
+        return Ok(Some((RespValue::Null, consumed))); // null bulk string ($-1\r\n)
+    };
+    if buf.len() < consumed + len + 2 {
+        return Ok(None); // payload and/or trailing CRLF hasn't fully arrived yet
+    }
+    let data = buf[consumed..consumed + len].to_vec();
+    consumed += len + 2;
+    Ok(Some((
+        RespValue::BulkString(String::from_utf8(data)?),
+        consumed,
+    )))
+}
+
+/// Caps how many elements a single array frame may declare. A client can't possibly have
+/// buffered more elements than bytes it has sent, but the declared length is read off the wire
+/// before any of those elements arrive, so without a cap `Vec::with_capacity(len)` would let a
+/// single small frame like `*999999999999\r\n` trigger an allocation large enough to abort the
+/// process.
+const MAX_ARRAY_LEN: usize = 1_024 * 1_024;
+
+fn parse_array(buf: &[u8]) -> anyhow::Result<Option<(RespValue, usize)>> {
+    let Some((len, mut offset)) = parse_length(buf)? else {
+        return Ok(None);
+    };
+    let Some(len) = len else {
+        return Ok(Some((RespValue::Null, offset))); // null array (*-1\r\n)
+    };
+    if len > MAX_ARRAY_LEN {
+        bail!(
+            "array length {} exceeds the maximum of {}",
+            len,
+            MAX_ARRAY_LEN
+        );
+    }
+    let mut elements = Vec::with_capacity(len);
+    for _ in 0..len {
+        match parse(&buf[offset..])? {
+            Some((value, consumed)) => {
+                elements.push(value);
+                offset += consumed;
+            }
+            None => return Ok(None), // wait for the rest of the array to arrive
+        }
+    }
+    Ok(Some((RespValue::Array(elements), offset)))
+}
+
+/// Parses the `$`/`*` length prefix shared by bulk strings and arrays. The inner `Option` is
+/// `None` when the prefix is the RESP null sentinel (`-1`).
+fn parse_length(buf: &[u8]) -> anyhow::Result<Option<(Option<usize>, usize)>> {
+    let Some((s, consumed)) = parse_line(buf)? else {
+        return Ok(None);
+    };
+    let n = s.parse::<i64>()?;
+    if n < 0 {
+        return Ok(Some((None, consumed)));
+    }
+    Ok(Some((Some(n as usize), consumed)))
+}
+
+/// Reads a CRLF-terminated line from the front of `buf`, returning its contents and the total
+/// number of bytes consumed (including the CRLF). Returns `None` if the terminator hasn't
+/// arrived yet; no scan position is kept, so the next call scans `buf` from the start again.
+fn parse_line(buf: &[u8]) -> anyhow::Result<Option<(String, usize)>> {
+    let Some(idx) = memchr(b'\r', buf) else {
+        return Ok(None);
+    };
+    if idx + 1 >= buf.len() {
+        return Ok(None); // the \n may still be on its way
+    }
+    if buf[idx + 1] != b'\n' {
+        bail!("expected CRLF line terminator");
+    }
+    Ok(Some((
+        std::str::from_utf8(&buf[..idx])?.to_string(),
+        idx + 2,
+    )))
+}
+
+fn write_value(value: &RespValue, dst: &mut BytesMut) {
+    match value {
+        RespValue::SimpleString(s) => write_line(dst, b'+', s),
+        RespValue::Error(s) => write_line(dst, b'-', s),
+        RespValue::Integer(n) => write_line(dst, b':', &n.to_string()),
+        RespValue::BulkString(s) => {
+            write_line(dst, b'$', &s.len().to_string());
+            dst.put_slice(s.as_bytes());
+            dst.put_slice(b"\r\n");
+        }
+        RespValue::Null => dst.put_slice(b"$-1\r\n"),
+        RespValue::Array(values) => {
+            write_line(dst, b'*', &values.len().to_string());
+            for value in values {
+                write_value(value, dst);
+            }
+        }
+    }
+}
+
+fn write_line(dst: &mut BytesMut, magic: u8, body: &str) {
+    dst.reserve(body.len() + 3);
+    dst.put_u8(magic);
+    dst.put_slice(body.as_bytes());
+    dst.put_slice(b"\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn decode_all(codec: &mut RespCodec, input: &[u8]) -> Result<Vec<RespValue>> {
+        let mut buf = BytesMut::from(input);
+        let mut values = Vec::new();
+        while let Some(value) = codec.decode(&mut buf)? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+
+    #[test]
+    fn test_decode_simple_string() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"+PING\r\n")?;
+        assert_eq!(values, vec![RespValue::SimpleString("PING".into())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_error() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"-ERR unknown command\r\n")?;
+        assert_eq!(values, vec![RespValue::Error("ERR unknown command".into())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_integer() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b":1000\r\n")?;
+        assert_eq!(values, vec![RespValue::Integer(1000)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bulk_string_empty() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"$0\r\n\r\n")?;
+        assert_eq!(values, vec![RespValue::BulkString("".into())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bulk_string() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"$5\r\nhello\r\n")?;
+        assert_eq!(values, vec![RespValue::BulkString("hello".into())]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_bulk_string_null() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"$-1\r\n")?;
+        assert_eq!(values, vec![RespValue::Null]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_array_empty() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"*0\r\n")?;
+        assert_eq!(values, vec![RespValue::Array(vec![])]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_array_length_over_max_is_rejected() {
+        let mut buf = BytesMut::from(&b"*999999999999\r\n"[..]);
+        assert!(RespCodec.decode(&mut buf).is_err());
+    }
+
+    #[test]
+    fn test_decode_array_null() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"*-1\r\n")?;
+        assert_eq!(values, vec![RespValue::Null]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_array_bulk_strings() -> Result<()> {
+        let values = decode_all(&mut RespCodec, b"*2\r\n$5\r\nhello\r\n$5\r\nworld\r\n")?;
+        assert_eq!(
+            values,
+            vec![RespValue::Array(vec![
+                RespValue::BulkString("hello".into()),
+                RespValue::BulkString("world".into())
+            ])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_waits_for_split_frame() -> Result<()> {
+        let mut codec = RespCodec;
+        let mut buf = BytesMut::from(&b"*2\r\n$5\r\nhel"[..]);
+        assert!(codec.decode(&mut buf)?.is_none());
+        buf.extend_from_slice(b"lo\r\n$5\r\nworld\r\n");
+        assert_eq!(
+            codec.decode(&mut buf)?,
+            Some(RespValue::Array(vec![
+                RespValue::BulkString("hello".into()),
+                RespValue::BulkString("world".into())
+            ]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_then_decode_roundtrip() -> Result<()> {
+        let mut codec = RespCodec;
+        let value = RespValue::Array(vec![
+            RespValue::BulkString("hello".into()),
+            RespValue::Integer(42),
+            RespValue::Null,
+        ]);
+        let mut buf = BytesMut::new();
+        codec.encode(
+            RespValue::Array(vec![
+                RespValue::BulkString("hello".into()),
+                RespValue::Integer(42),
+                RespValue::Null,
+            ]),
+            &mut buf,
+        )?;
+        assert_eq!(codec.decode(&mut buf)?, Some(value));
+        Ok(())
+    }
+
+    fn encode(value: RespValue) -> Result<BytesMut> {
+        let mut buf = BytesMut::new();
+        RespCodec.encode(value, &mut buf)?;
+        Ok(buf)
+    }
+
+    #[test]
+    fn test_encode_simple_string() -> Result<()> {
+        assert_eq!(
+            encode(RespValue::SimpleString("PONG".into()))?,
+            &b"+PONG\r\n"[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_error() -> Result<()> {
+        assert_eq!(
+            encode(RespValue::Error("ERR unknown command".into()))?,
+            &b"-ERR unknown command\r\n"[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_integer() -> Result<()> {
+        assert_eq!(encode(RespValue::Integer(1000))?, &b":1000\r\n"[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_bulk_string() -> Result<()> {
+        assert_eq!(
+            encode(RespValue::BulkString("hello".into()))?,
+            &b"$5\r\nhello\r\n"[..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_null() -> Result<()> {
+        assert_eq!(encode(RespValue::Null)?, &b"$-1\r\n"[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_array() -> Result<()> {
+        assert_eq!(
+            encode(RespValue::Array(vec![
+                RespValue::BulkString("hello".into()),
+                RespValue::Integer(42),
+            ]))?,
+            &b"*2\r\n$5\r\nhello\r\n:42\r\n"[..]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+use std::time::{Duration, Instant};
+
+use crate::resp_protocol::RespValue;
+use crate::store::Store;
+
+/// Dispatches a decoded command line (element 0 is the command name, the rest are its
+/// arguments) against `store` and produces the `RespValue` reply to send back to the client.
+pub fn dispatch(store: &Store, command: &[String]) -> RespValue {
+    let Some((name, args)) = command.split_first() else {
+        return RespValue::Error("ERR empty command".into());
+    };
+    match name.to_uppercase().as_str() {
+        "PING" => RespValue::SimpleString("PONG".into()),
+        "GET" => cmd_get(store, args),
+        "SET" => cmd_set(store, args),
+        "DEL" => RespValue::Integer(store.del(args)),
+        "EXISTS" => RespValue::Integer(store.exists(args)),
+        "EXPIRE" => cmd_expire(store, args),
+        "INCR" => cmd_incr(store, args, 1),
+        "DECR" => cmd_incr(store, args, -1),
+        _ => RespValue::Error(format!("ERR unknown command '{}'", name)),
+    }
+}
+
+fn cmd_get(store: &Store, args: &[String]) -> RespValue {
+    let [key] = args else {
+        return RespValue::Error("ERR wrong number of arguments for 'get' command".into());
+    };
+    match store.get(key) {
+        Some(value) => RespValue::BulkString(value),
+        None => RespValue::Null,
+    }
+}
+
+fn cmd_set(store: &Store, args: &[String]) -> RespValue {
+    let [key, value, rest @ ..] = args else {
+        return RespValue::Error("ERR wrong number of arguments for 'set' command".into());
+    };
+    let expires_at = match rest {
+        [] => None,
+        [opt, seconds] if opt.eq_ignore_ascii_case("EX") => match seconds.parse::<u64>() {
+            Ok(seconds) => match checked_deadline(Duration::from_secs(seconds)) {
+                Ok(at) => Some(at),
+                Err(err) => return err,
+            },
+            Err(_) => {
+                return RespValue::Error("ERR value is not an integer or out of range".into())
+            }
+        },
+        [opt, millis] if opt.eq_ignore_ascii_case("PX") => match millis.parse::<u64>() {
+            Ok(millis) => match checked_deadline(Duration::from_millis(millis)) {
+                Ok(at) => Some(at),
+                Err(err) => return err,
+            },
+            Err(_) => {
+                return RespValue::Error("ERR value is not an integer or out of range".into())
+            }
+        },
+        _ => return RespValue::Error("ERR syntax error".into()),
+    };
+    store.set(key.clone(), value.clone(), expires_at);
+    RespValue::SimpleString("OK".into())
+}
+
+/// Adds `ttl` to the current time, returning a RESP error instead of letting a client-supplied
+/// duration overflow what `Instant` can represent (which would panic).
+fn checked_deadline(ttl: Duration) -> Result<Instant, RespValue> {
+    Instant::now()
+        .checked_add(ttl)
+        .ok_or_else(|| RespValue::Error("ERR invalid expire time in 'set' command".into()))
+}
+
+fn cmd_expire(store: &Store, args: &[String]) -> RespValue {
+    let [key, seconds] = args else {
+        return RespValue::Error("ERR wrong number of arguments for 'expire' command".into());
+    };
+    match seconds.parse::<u64>() {
+        Ok(seconds) => match store.expire(key, Duration::from_secs(seconds)) {
+            Ok(present) => RespValue::Integer(present as i64),
+            Err(err) => RespValue::Error(format!("ERR {}", err)),
+        },
+        Err(_) => RespValue::Error("ERR value is not an integer or out of range".into()),
+    }
+}
+
+fn cmd_incr(store: &Store, args: &[String], delta: i64) -> RespValue {
+    let [key] = args else {
+        return RespValue::Error("ERR wrong number of arguments for 'incr'/'decr' command".into());
+    };
+    match store.incr_by(key, delta) {
+        Ok(n) => RespValue::Integer(n),
+        Err(err) => RespValue::Error(format!("ERR {}", err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_then_get() {
+        let store = Store::new();
+        assert_eq!(
+            dispatch(&store, &["SET".into(), "key".into(), "value".into()]),
+            RespValue::SimpleString("OK".into())
+        );
+        assert_eq!(
+            dispatch(&store, &["GET".into(), "key".into()]),
+            RespValue::BulkString("value".into())
+        );
+    }
+
+    #[test]
+    fn test_get_missing_key() {
+        let store = Store::new();
+        assert_eq!(
+            dispatch(&store, &["GET".into(), "missing".into()]),
+            RespValue::Null
+        );
+    }
+
+    #[test]
+    fn test_del_and_exists() {
+        let store = Store::new();
+        dispatch(&store, &["SET".into(), "key".into(), "value".into()]);
+        assert_eq!(
+            dispatch(&store, &["EXISTS".into(), "key".into()]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            dispatch(&store, &["DEL".into(), "key".into()]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            dispatch(&store, &["EXISTS".into(), "key".into()]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_incr_and_decr() {
+        let store = Store::new();
+        assert_eq!(
+            dispatch(&store, &["INCR".into(), "counter".into()]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            dispatch(&store, &["INCR".into(), "counter".into()]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            dispatch(&store, &["DECR".into(), "counter".into()]),
+            RespValue::Integer(1)
+        );
+    }
+
+    #[test]
+    fn test_incr_on_non_integer_value() {
+        let store = Store::new();
+        dispatch(&store, &["SET".into(), "key".into(), "not-a-number".into()]);
+        assert_eq!(
+            dispatch(&store, &["INCR".into(), "key".into()]),
+            RespValue::Error("ERR value is not an integer or out of range".into())
+        );
+    }
+
+    #[test]
+    fn test_set_with_expiry_in_the_past_expires_immediately() {
+        let store = Store::new();
+        dispatch(
+            &store,
+            &[
+                "SET".into(),
+                "key".into(),
+                "value".into(),
+                "EX".into(),
+                "0".into(),
+            ],
+        );
+        assert_eq!(
+            dispatch(&store, &["GET".into(), "key".into()]),
+            RespValue::Null
+        );
+    }
+
+    #[test]
+    fn test_set_with_ex_overflowing_instant_returns_error_instead_of_panicking() {
+        let store = Store::new();
+        assert_eq!(
+            dispatch(
+                &store,
+                &[
+                    "SET".into(),
+                    "key".into(),
+                    "value".into(),
+                    "EX".into(),
+                    u64::MAX.to_string(),
+                ]
+            ),
+            RespValue::Error("ERR invalid expire time in 'set' command".into())
+        );
+    }
+
+    #[test]
+    fn test_expire_overflowing_instant_returns_error_instead_of_panicking() {
+        let store = Store::new();
+        dispatch(&store, &["SET".into(), "key".into(), "value".into()]);
+        assert_eq!(
+            dispatch(
+                &store,
+                &["EXPIRE".into(), "key".into(), u64::MAX.to_string()]
+            ),
+            RespValue::Error("ERR invalid expire time".into())
+        );
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let store = Store::new();
+        assert_eq!(
+            dispatch(&store, &["FOOBAR".into()]),
+            RespValue::Error("ERR unknown command 'FOOBAR'".into())
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::AbortHandle;
+
+use crate::commands;
+use crate::pubsub::PubSub;
+use crate::resp_protocol::RespValue;
+use crate::store::Store;
+
+/// Drives a single client connection to completion over `transport`: decodes commands,
+/// dispatches them against `store`, and handles the stateful `SUBSCRIBE`/`PUBLISH` commands
+/// against `pubsub`. Once subscribed to at least one channel, the connection concurrently
+/// waits on both new client frames and messages pushed from other connections' `PUBLISH`es.
+///
+/// `transport` only needs to speak `RespValue` frames, so the exact same loop drives both the
+/// raw-TCP/TLS path (a `Framed<_, RespCodec>`) and the WebSocket path (a `WsTransport`).
+pub async fn handle<T>(mut transport: T, store: Arc<Store>, pubsub: Arc<PubSub>) -> Result<()>
+where
+    T: Stream<Item = Result<RespValue>> + Sink<RespValue, Error = anyhow::Error> + Unpin + Send,
+{
+    let (message_tx, mut messages) = mpsc::unbounded_channel::<(String, String)>();
+    let mut subscriptions: HashMap<String, AbortHandle> = HashMap::new();
+
+    // Run the connection loop to completion before cleaning up, so a `?`-propagated error or an
+    // abrupt disconnect still aborts every `spawn_forwarder` task instead of leaking them.
+    let result = run(
+        &mut transport,
+        &store,
+        &pubsub,
+        &message_tx,
+        &mut messages,
+        &mut subscriptions,
+    )
+    .await;
+
+    for handle in subscriptions.into_values() {
+        handle.abort();
+    }
+    result
+}
+
+async fn run<T>(
+    transport: &mut T,
+    store: &Store,
+    pubsub: &PubSub,
+    message_tx: &mpsc::UnboundedSender<(String, String)>,
+    messages: &mut mpsc::UnboundedReceiver<(String, String)>,
+    subscriptions: &mut HashMap<String, AbortHandle>,
+) -> Result<()>
+where
+    T: Stream<Item = Result<RespValue>> + Sink<RespValue, Error = anyhow::Error> + Unpin + Send,
+{
+    loop {
+        tokio::select! {
+            frame = transport.next() => {
+                let Some(frame) = frame else { break };
+                match frame? {
+                    RespValue::Array(command) => {
+                        let mut args = Vec::with_capacity(command.len());
+                        for value in command {
+                            match value {
+                                RespValue::BulkString(s) => args.push(s),
+                                other => bail!("unexpected command element type from client: {:?}", other),
+                            }
+                        }
+                        let replies = handle_command(store, pubsub, message_tx, subscriptions, args);
+                        for reply in replies {
+                            transport.send(reply).await?;
+                        }
+                    }
+                    _ => bail!("unexpected root value type from client"),
+                }
+            }
+            Some((channel, payload)) = messages.recv() => {
+                transport.send(RespValue::Array(vec![
+                    RespValue::BulkString("message".into()),
+                    RespValue::BulkString(channel),
+                    RespValue::BulkString(payload),
+                ])).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handles one decoded command line, returning the reply frame(s) to send back. `SUBSCRIBE`
+/// and `UNSUBSCRIBE` reply once per channel, so this can return more than one `RespValue`.
+fn handle_command(
+    store: &Store,
+    pubsub: &PubSub,
+    message_tx: &mpsc::UnboundedSender<(String, String)>,
+    subscriptions: &mut HashMap<String, AbortHandle>,
+    args: Vec<String>,
+) -> Vec<RespValue> {
+    let Some(name) = args.first() else {
+        return vec![RespValue::Error("ERR empty command".into())];
+    };
+    let rest = &args[1..];
+    match name.to_uppercase().as_str() {
+        "SUBSCRIBE" if rest.is_empty() => vec![RespValue::Error(
+            "ERR wrong number of arguments for 'subscribe' command".into(),
+        )],
+        "SUBSCRIBE" => rest
+            .iter()
+            .map(|channel| {
+                subscriptions.entry(channel.clone()).or_insert_with(|| {
+                    spawn_forwarder(
+                        pubsub.subscribe(channel),
+                        channel.clone(),
+                        message_tx.clone(),
+                    )
+                });
+                RespValue::Array(vec![
+                    RespValue::BulkString("subscribe".into()),
+                    RespValue::BulkString(channel.clone()),
+                    RespValue::Integer(subscriptions.len() as i64),
+                ])
+            })
+            .collect(),
+        "UNSUBSCRIBE" => {
+            let channels: Vec<String> = if rest.is_empty() {
+                subscriptions.keys().cloned().collect()
+            } else {
+                rest.to_vec()
+            };
+            if channels.is_empty() {
+                return vec![RespValue::Array(vec![
+                    RespValue::BulkString("unsubscribe".into()),
+                    RespValue::Null,
+                    RespValue::Integer(0),
+                ])];
+            }
+            channels
+                .into_iter()
+                .map(|channel| {
+                    if let Some(handle) = subscriptions.remove(&channel) {
+                        handle.abort();
+                    }
+                    RespValue::Array(vec![
+                        RespValue::BulkString("unsubscribe".into()),
+                        RespValue::BulkString(channel),
+                        RespValue::Integer(subscriptions.len() as i64),
+                    ])
+                })
+                .collect()
+        }
+        "PUBLISH" => match rest {
+            [channel, payload] => {
+                let n = pubsub.publish(channel, payload.clone());
+                vec![RespValue::Integer(n as i64)]
+            }
+            _ => vec![RespValue::Error(
+                "ERR wrong number of arguments for 'publish' command".into(),
+            )],
+        },
+        _ => vec![commands::dispatch(store, &args)],
+    }
+}
+
+/// Spawns a task that forwards every message received on `receiver` into `message_tx`,
+/// tagging it with `channel`. Returns an `AbortHandle` so `UNSUBSCRIBE` can stop it.
+fn spawn_forwarder(
+    mut receiver: broadcast::Receiver<String>,
+    channel: String,
+    message_tx: mpsc::UnboundedSender<(String, String)>,
+) -> AbortHandle {
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(payload) => {
+                    if message_tx.send((channel.clone(), payload)).is_err() {
+                        break; // the connection this was forwarding to is gone
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue, // drop missed messages
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+    .abort_handle()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(words: &[&str]) -> Vec<String> {
+        words.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_bare_subscribe_is_wrong_arity_error() {
+        let store = Store::new();
+        let pubsub = PubSub::new();
+        let (message_tx, _messages) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+        let replies = handle_command(
+            &store,
+            &pubsub,
+            &message_tx,
+            &mut subscriptions,
+            args(&["SUBSCRIBE"]),
+        );
+        assert_eq!(
+            replies,
+            vec![RespValue::Error(
+                "ERR wrong number of arguments for 'subscribe' command".into()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_replies_once_per_channel_with_running_count() {
+        let store = Store::new();
+        let pubsub = PubSub::new();
+        let (message_tx, _messages) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+        let replies = handle_command(
+            &store,
+            &pubsub,
+            &message_tx,
+            &mut subscriptions,
+            args(&["SUBSCRIBE", "a", "b"]),
+        );
+        assert_eq!(
+            replies,
+            vec![
+                RespValue::Array(vec![
+                    RespValue::BulkString("subscribe".into()),
+                    RespValue::BulkString("a".into()),
+                    RespValue::Integer(1),
+                ]),
+                RespValue::Array(vec![
+                    RespValue::BulkString("subscribe".into()),
+                    RespValue::BulkString("b".into()),
+                    RespValue::Integer(2),
+                ]),
+            ]
+        );
+        assert_eq!(subscriptions.len(), 2);
+        for handle in subscriptions.into_values() {
+            handle.abort();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribe_removes_the_channel_and_decrements_count() {
+        let store = Store::new();
+        let pubsub = PubSub::new();
+        let (message_tx, _messages) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+        handle_command(
+            &store,
+            &pubsub,
+            &message_tx,
+            &mut subscriptions,
+            args(&["SUBSCRIBE", "a", "b"]),
+        );
+        let replies = handle_command(
+            &store,
+            &pubsub,
+            &message_tx,
+            &mut subscriptions,
+            args(&["UNSUBSCRIBE", "a"]),
+        );
+        assert_eq!(
+            replies,
+            vec![RespValue::Array(vec![
+                RespValue::BulkString("unsubscribe".into()),
+                RespValue::BulkString("a".into()),
+                RespValue::Integer(1),
+            ])]
+        );
+        assert!(!subscriptions.contains_key("a"));
+        for handle in subscriptions.into_values() {
+            handle.abort();
+        }
+    }
+
+    #[test]
+    fn test_unsubscribe_with_nothing_subscribed_replies_once() {
+        let store = Store::new();
+        let pubsub = PubSub::new();
+        let (message_tx, _messages) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+        let replies = handle_command(
+            &store,
+            &pubsub,
+            &message_tx,
+            &mut subscriptions,
+            args(&["UNSUBSCRIBE"]),
+        );
+        assert_eq!(
+            replies,
+            vec![RespValue::Array(vec![
+                RespValue::BulkString("unsubscribe".into()),
+                RespValue::Null,
+                RespValue::Integer(0),
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_publish_wrong_arity() {
+        let store = Store::new();
+        let pubsub = PubSub::new();
+        let (message_tx, _messages) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+        let replies = handle_command(
+            &store,
+            &pubsub,
+            &message_tx,
+            &mut subscriptions,
+            args(&["PUBLISH", "channel"]),
+        );
+        assert_eq!(
+            replies,
+            vec![RespValue::Error(
+                "ERR wrong number of arguments for 'publish' command".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_command_falls_through_to_command_dispatch() {
+        let store = Store::new();
+        let pubsub = PubSub::new();
+        let (message_tx, _messages) = mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::new();
+        let replies = handle_command(
+            &store,
+            &pubsub,
+            &message_tx,
+            &mut subscriptions,
+            args(&["PING"]),
+        );
+        assert_eq!(replies, vec![RespValue::SimpleString("PONG".into())]);
+    }
+}
@@ -0,0 +1,281 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use anyhow::Result;
+use async_compression::tokio::bufread::ZstdDecoder;
+use async_compression::tokio::write::ZstdEncoder;
+use tokio::io::{
+    AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf, ReadHalf, WriteHalf,
+};
+
+/// Magic byte that opens the handshake. A client skipping straight to RESP sends `+`, `-`,
+/// `:`, `$`, or `*` as its first byte, none of which collide with this.
+const HANDSHAKE_MAGIC: u8 = 0x00;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+enum CodecId {
+    None = 0,
+    Zstd = 1,
+}
+
+/// Reads the optional compression handshake off the front of `stream` and returns the byte
+/// stream to hand to the RESP codec from then on. A client that opens with [`HANDSHAKE_MAGIC`]
+/// is expected to follow it with a length-prefixed list of codec IDs it supports; the server
+/// picks zstd if it is anywhere in that list (otherwise no compression), writes that one byte
+/// back, and wraps the rest of the connection in the matching compressor/decompressor. A
+/// client that sends no handshake at all falls back to the uncompressed path unchanged.
+pub async fn negotiate<S>(mut stream: S) -> Result<HandshakeStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let first_byte = stream.read_u8().await?;
+    if first_byte != HANDSHAKE_MAGIC {
+        return Ok(HandshakeStream::Plain(PrefixedStream::new(
+            first_byte, stream,
+        )));
+    }
+
+    let offered_len = stream.read_u8().await?;
+    let mut offered = vec![0u8; offered_len as usize];
+    stream.read_exact(&mut offered).await?;
+
+    let chosen = if offered.contains(&(CodecId::Zstd as u8)) {
+        CodecId::Zstd
+    } else {
+        CodecId::None
+    };
+    stream.write_u8(chosen as u8).await?;
+    stream.flush().await?;
+
+    Ok(match chosen {
+        CodecId::Zstd => HandshakeStream::Compressed(CompressedStream::new(stream)),
+        CodecId::None => HandshakeStream::Plain(PrefixedStream::empty(stream)),
+    })
+}
+
+/// Either the plain byte stream (with any byte already consumed while peeking for the
+/// handshake magic spliced back onto the front) or one wrapped in zstd compression.
+pub enum HandshakeStream<S> {
+    Plain(PrefixedStream<S>),
+    Compressed(CompressedStream<S>),
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for HandshakeStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            HandshakeStream::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            HandshakeStream::Compressed(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for HandshakeStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            HandshakeStream::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            HandshakeStream::Compressed(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            HandshakeStream::Plain(s) => Pin::new(s).poll_flush(cx),
+            HandshakeStream::Compressed(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            HandshakeStream::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            HandshakeStream::Compressed(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Wraps a stream with a single byte that was already read off its front, so that byte is
+/// served back as the first thing a reader sees. Used to un-consume the byte peeked to detect
+/// whether a client opened with the compression handshake or went straight to RESP.
+pub struct PrefixedStream<S> {
+    prefix: Option<u8>,
+    inner: S,
+}
+
+impl<S> PrefixedStream<S> {
+    fn new(byte: u8, inner: S) -> Self {
+        Self {
+            prefix: Some(byte),
+            inner,
+        }
+    }
+
+    fn empty(inner: S) -> Self {
+        Self {
+            prefix: None,
+            inner,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for PrefixedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if let Some(byte) = this.prefix.take() {
+            if buf.remaining() > 0 {
+                buf.put_slice(&[byte]);
+            } else {
+                this.prefix = Some(byte); // no room this call; try again on the next one
+            }
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for PrefixedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// A byte stream whose incoming bytes are zstd-decompressed and whose outgoing bytes are
+/// zstd-compressed, so everything above it (the RESP codec) never has to know compression is
+/// in play.
+pub struct CompressedStream<S> {
+    reader: ZstdDecoder<BufReader<ReadHalf<S>>>,
+    writer: ZstdEncoder<WriteHalf<S>>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> CompressedStream<S> {
+    fn new(stream: S) -> Self {
+        let (reader, writer) = tokio::io::split(stream);
+        Self {
+            reader: ZstdDecoder::new(BufReader::new(reader)),
+            writer: ZstdEncoder::new(writer),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for CompressedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().reader).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for CompressedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().writer).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prefixed_stream_replays_consumed_byte_before_inner_bytes() {
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_all(b"ello").await.unwrap();
+        client.flush().await.unwrap();
+
+        let mut stream = PrefixedStream::new(b'h', server);
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_without_magic_falls_back_to_plain_preserving_first_byte() {
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_all(b"+PING\r\n").await.unwrap();
+        client.flush().await.unwrap();
+
+        let mut stream = negotiate(server).await.unwrap();
+        assert!(matches!(stream, HandshakeStream::Plain(_)));
+        let mut buf = [0u8; 7];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"+PING\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_with_unsupported_codec_falls_back_to_none() {
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_u8(HANDSHAKE_MAGIC).await.unwrap();
+        client.write_u8(1).await.unwrap();
+        client.write_u8(42).await.unwrap(); // an offered codec id the server doesn't know
+        client.flush().await.unwrap();
+
+        let mut stream = negotiate(server).await.unwrap();
+        assert_eq!(client.read_u8().await.unwrap(), CodecId::None as u8);
+        assert!(matches!(stream, HandshakeStream::Plain(_)));
+
+        client.write_all(b"+OK\r\n").await.unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"+OK\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_negotiate_with_zstd_offered_round_trips_compressed_data() {
+        let (client, server) = tokio::io::duplex(4096);
+        let (mut client_read, client_write) = tokio::io::split(client);
+
+        let mut client_write = client_write;
+        client_write.write_u8(HANDSHAKE_MAGIC).await.unwrap();
+        client_write.write_u8(1).await.unwrap();
+        client_write.write_u8(CodecId::Zstd as u8).await.unwrap();
+        client_write.flush().await.unwrap();
+
+        let mut stream = negotiate(server).await.unwrap();
+        assert_eq!(client_read.read_u8().await.unwrap(), CodecId::Zstd as u8);
+        assert!(matches!(stream, HandshakeStream::Compressed(_)));
+
+        let mut encoder = ZstdEncoder::new(client_write);
+        encoder.write_all(b"+PING\r\n").await.unwrap();
+        encoder.flush().await.unwrap();
+
+        let mut buf = [0u8; 7];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"+PING\r\n");
+    }
+}
@@ -0,0 +1,12 @@
+mod codec;
+mod commands;
+mod connection;
+mod handshake;
+mod pubsub;
+mod resp_protocol;
+mod server;
+mod store;
+mod tls;
+mod ws;
+
+pub use server::Server;
@@ -1,9 +1,5 @@
-mod decoder;
-mod resp_protocol;
-mod server;
-
 use anyhow::Result;
-use server::Server;
+use redis_rs::Server;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -0,0 +1,36 @@
+use dashmap::DashMap;
+use tokio::sync::broadcast;
+
+/// Number of messages a lagging subscriber can fall behind by before old ones are dropped.
+const CHANNEL_CAPACITY: usize = 128;
+
+/// The shared channel registry behind `SUBSCRIBE`/`PUBLISH`. Each channel name maps to a
+/// `broadcast` sender that every subscribed connection holds a receiver for; the sender (and
+/// therefore the channel entry) is created lazily on first subscribe and simply stays around
+/// afterwards, even once every subscriber has gone.
+#[derive(Default)]
+pub struct PubSub {
+    channels: DashMap<String, broadcast::Sender<String>>,
+}
+
+impl PubSub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, channel: &str) -> broadcast::Receiver<String> {
+        self.channels
+            .entry(channel.to_string())
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Publishes `payload` to `channel`, returning the number of subscribers it was delivered
+    /// to (`0` if nobody is subscribed).
+    pub fn publish(&self, channel: &str, payload: String) -> usize {
+        match self.channels.get(channel) {
+            Some(sender) => sender.send(payload).unwrap_or(0),
+            None => 0,
+        }
+    }
+}
@@ -1,6 +1,9 @@
 #[derive(Debug, PartialEq, Eq)]
 pub enum RespValue {
     SimpleString(String),
+    Error(String),
+    Integer(i64),
     BulkString(String),
+    Null,
     Array(Vec<RespValue>),
 }
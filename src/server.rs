@@ -1,64 +1,113 @@
-use anyhow::{bail, Result};
-use tokio::{
-    io::{AsyncWriteExt, BufReader},
-    net::{TcpListener, TcpStream, ToSocketAddrs},
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::codec::Framed;
+
+use crate::{
+    codec::RespCodec, connection, handshake, pubsub::PubSub, store::Store, tls, ws::WsTransport,
 };
 
-use crate::{decoder::Decoder, resp_protocol::RespValue};
+#[derive(Clone)]
+enum Transport {
+    Plain,
+    Tls(TlsAcceptor),
+    WebSocket,
+}
 
 pub struct Server {
     listener: TcpListener,
+    transport: Transport,
+    store: Arc<Store>,
+    pubsub: Arc<PubSub>,
 }
 
 impl Server {
     pub async fn new<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Self::bind(addr, Transport::Plain).await
+    }
+
+    /// Like [`Server::new`], but terminates TLS on every accepted connection using the
+    /// certificate chain and private key found at `cert_path`/`key_path` (PEM-encoded).
+    pub async fn new_tls<A: ToSocketAddrs>(
+        addr: A,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> Result<Self> {
+        let acceptor = tls::build_acceptor(cert_path.as_ref(), key_path.as_ref())?;
+        Self::bind(addr, Transport::Tls(acceptor)).await
+    }
+
+    /// Like [`Server::new`], but accepts WebSocket connections and tunnels RESP over their
+    /// binary frames instead of speaking RESP directly over the raw TCP stream.
+    pub async fn new_ws<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        Self::bind(addr, Transport::WebSocket).await
+    }
+
+    async fn bind<A: ToSocketAddrs>(addr: A, transport: Transport) -> Result<Self> {
         Ok(Self {
             listener: TcpListener::bind(addr).await?,
+            transport,
+            store: Arc::new(Store::new()),
+            pubsub: Arc::new(PubSub::new()),
         })
     }
 
     pub async fn listen(&mut self) -> Result<()> {
         loop {
             let (stream, _) = self.listener.accept().await?;
-            tokio::spawn(async move {
-                if let Err(err) = handle_connection(stream).await {
-                    println!("{:?}", err);
+            let store = Arc::clone(&self.store);
+            let pubsub = Arc::clone(&self.pubsub);
+            match self.transport.clone() {
+                Transport::Plain => {
+                    tokio::spawn(async move {
+                        match handshake::negotiate(stream).await {
+                            Ok(stream) => {
+                                let transport = Framed::new(stream, RespCodec);
+                                if let Err(err) = connection::handle(transport, store, pubsub).await
+                                {
+                                    println!("{:?}", err);
+                                }
+                            }
+                            Err(err) => println!("compression handshake failed: {:?}", err),
+                        }
+                    });
                 }
-            });
-        }
-    }
-}
-
-async fn handle_connection(mut stream: TcpStream) -> Result<()> {
-    let (reader, mut writer) = stream.split();
-    let mut decoder = Decoder::new(BufReader::new(reader));
-    loop {
-        match decoder.next().await {
-            Some(Ok(RespValue::Array(commands))) => {
-                for command in commands {
-                    if let RespValue::BulkString(cmd) = command {
-                        process_command(&mut writer, &cmd).await?;
-                    } else {
-                        bail!("unexpected command element type from client: {:?}", command);
-                    }
+                Transport::Tls(acceptor) => {
+                    tokio::spawn(async move {
+                        match acceptor.accept(stream).await {
+                            Ok(stream) => match handshake::negotiate(stream).await {
+                                Ok(stream) => {
+                                    let transport = Framed::new(stream, RespCodec);
+                                    if let Err(err) =
+                                        connection::handle(transport, store, pubsub).await
+                                    {
+                                        println!("{:?}", err);
+                                    }
+                                }
+                                Err(err) => println!("compression handshake failed: {:?}", err),
+                            },
+                            Err(err) => println!("TLS handshake failed: {:?}", err),
+                        }
+                    });
+                }
+                Transport::WebSocket => {
+                    tokio::spawn(async move {
+                        match tokio_tungstenite::accept_async(stream).await {
+                            Ok(ws) => {
+                                let transport = WsTransport::new(ws);
+                                if let Err(err) = connection::handle(transport, store, pubsub).await
+                                {
+                                    println!("{:?}", err);
+                                }
+                            }
+                            Err(err) => println!("websocket handshake failed: {:?}", err),
+                        }
+                    });
                 }
             }
-            Some(Ok(_)) => bail!("unexpected root value type from client"),
-            Some(Err(err)) => return Err(err),
-            None => return Ok(()), // end of stream
         }
     }
 }
-
-async fn process_command<W>(writer: &mut W, cmd: &str) -> Result<()>
-where
-    W: AsyncWriteExt + Unpin,
-{
-    match cmd.to_uppercase().as_str() {
-        "PING" => {
-            writer.write_all("+PONG\r\n".as_bytes()).await?;
-        }
-        _ => println!("Unexpected command: {}", cmd),
-    };
-    Ok(())
-}
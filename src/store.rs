@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use dashmap::mapref::entry::Entry as MapEntry;
+use dashmap::DashMap;
+
+struct Entry {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+impl Entry {
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| Instant::now() >= at)
+    }
+}
+
+/// The in-memory keyspace shared across all connections. Cheap to clone an `Arc` of and hand
+/// to each spawned connection task; `DashMap` gives us lock striping across keys so one
+/// connection's `SET` doesn't block another's unrelated `GET`.
+#[derive(Default)]
+pub struct Store {
+    entries: DashMap<String, Entry>,
+}
+
+impl Store {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let entry = self.entries.get(key)?;
+        if entry.is_expired() {
+            drop(entry);
+            self.entries.remove(key);
+            return None;
+        }
+        Some(entry.value.clone())
+    }
+
+    pub fn set(&self, key: String, value: String, expires_at: Option<Instant>) {
+        self.entries.insert(key, Entry { value, expires_at });
+    }
+
+    pub fn del(&self, keys: &[String]) -> i64 {
+        keys.iter()
+            .filter(|key| self.entries.remove(key.as_str()).is_some())
+            .count() as i64
+    }
+
+    pub fn exists(&self, keys: &[String]) -> i64 {
+        keys.iter().filter(|key| self.get(key).is_some()).count() as i64
+    }
+
+    /// Sets a TTL on an existing, non-expired key. Returns whether the key was present. Errors
+    /// if `ttl` is too large for `Instant` to represent, rather than letting the addition panic.
+    pub fn expire(&self, key: &str, ttl: Duration) -> Result<bool> {
+        if self.get(key).is_none() {
+            return Ok(false);
+        }
+        let deadline = Instant::now()
+            .checked_add(ttl)
+            .ok_or_else(|| anyhow!("invalid expire time"))?;
+        match self.entries.get_mut(key) {
+            Some(mut entry) => {
+                entry.expires_at = Some(deadline);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Adds `delta` to the integer stored at `key` (treating a missing key as `0`) and returns
+    /// the new value, preserving any existing TTL.
+    pub fn incr_by(&self, key: &str, delta: i64) -> Result<i64> {
+        if matches!(self.entries.get(key), Some(entry) if entry.is_expired()) {
+            self.entries.remove(key);
+        }
+        match self.entries.entry(key.to_string()) {
+            MapEntry::Occupied(mut occupied) => {
+                let current = occupied
+                    .get()
+                    .value
+                    .parse::<i64>()
+                    .map_err(|_| anyhow!("value is not an integer or out of range"))?;
+                let next = current
+                    .checked_add(delta)
+                    .ok_or_else(|| anyhow!("increment or decrement would overflow"))?;
+                occupied.get_mut().value = next.to_string();
+                Ok(next)
+            }
+            MapEntry::Vacant(vacant) => {
+                vacant.insert(Entry {
+                    value: delta.to_string(),
+                    expires_at: None,
+                });
+                Ok(delta)
+            }
+        }
+    }
+}
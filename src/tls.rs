@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use tokio_rustls::rustls::{self, Certificate, PrivateKey};
+use tokio_rustls::TlsAcceptor;
+
+/// Builds a `TlsAcceptor` from a PEM certificate chain and a PEM private key on disk.
+pub fn build_acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor> {
+    let certs = load_certs(cert_path)?;
+    let key = load_key(key_path)?;
+    let config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    Ok(rustls_pemfile::certs(&mut reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect())
+}
+
+fn load_key(path: &Path) -> Result<PrivateKey> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut reader)?;
+    if keys.is_empty() {
+        bail!("no PKCS#8 private key found in {:?}", path);
+    }
+    Ok(PrivateKey(keys.remove(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // A throwaway self-signed cert/key pair, valid for ten years, used only by these tests.
+    const TEST_CERT_PEM: &str = include_str!("../testdata/test_cert.pem");
+    const TEST_KEY_PEM: &str = include_str!("../testdata/test_key.pem");
+
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    fn write_temp_file(contents: &str) -> std::path::PathBuf {
+        let id = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "redis-rs-tls-test-{}-{}.pem",
+            std::process::id(),
+            id
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_build_acceptor_succeeds_with_valid_cert_and_key() {
+        let cert_path = write_temp_file(TEST_CERT_PEM);
+        let key_path = write_temp_file(TEST_KEY_PEM);
+        assert!(build_acceptor(&cert_path, &key_path).is_ok());
+    }
+
+    #[test]
+    fn test_load_certs_parses_every_certificate_in_the_file() {
+        let cert_path = write_temp_file(TEST_CERT_PEM);
+        let certs = load_certs(&cert_path).unwrap();
+        assert_eq!(certs.len(), 1);
+    }
+
+    #[test]
+    fn test_load_key_errors_when_file_has_no_pkcs8_key() {
+        // The cert file has no PRIVATE KEY block, so this should fail instead of panicking.
+        let not_a_key_path = write_temp_file(TEST_CERT_PEM);
+        assert!(load_key(&not_a_key_path).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use anyhow::anyhow;
+use bytes::BytesMut;
+use futures::{Sink, Stream};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::codec::RespCodec;
+use crate::resp_protocol::RespValue;
+
+/// Tunnels RESP over a WebSocket connection: each binary message is decoded as one `RespValue`
+/// frame, and each outgoing `RespValue` is sent back as a binary message. This lets
+/// `connection::handle` drive a WebSocket client with the exact same command-handling code as
+/// the raw-TCP/TLS path, since both sides only need to be a `Stream<Item = Result<RespValue>>`
+/// + `Sink<RespValue>`.
+pub struct WsTransport<S> {
+    inner: WebSocketStream<S>,
+}
+
+impl<S> WsTransport<S> {
+    pub fn new(inner: WebSocketStream<S>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S> Stream for WsTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = anyhow::Result<RespValue>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            return match Pin::new(&mut this.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(Message::Binary(bytes)))) => {
+                    Poll::Ready(Some(decode_frame(&bytes)))
+                }
+                Poll::Ready(Some(Ok(Message::Text(_)))) => Poll::Ready(Some(Err(anyhow!(
+                    "protocol error: expected binary RESP frames, got a text websocket frame"
+                )))),
+                // tungstenite answers Ping with Pong automatically; just keep polling.
+                Poll::Ready(Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_)))) => {
+                    continue
+                }
+                Poll::Ready(Some(Ok(Message::Close(_)))) => Poll::Ready(None),
+                Poll::Ready(Some(Err(err))) => Poll::Ready(Some(Err(err.into()))),
+                Poll::Ready(None) => Poll::Ready(None),
+                Poll::Pending => Poll::Pending,
+            };
+        }
+    }
+}
+
+fn decode_frame(bytes: &[u8]) -> anyhow::Result<RespValue> {
+    let mut buf = BytesMut::from(bytes);
+    match RespCodec.decode(&mut buf)? {
+        Some(value) => Ok(value),
+        None => Err(anyhow!("incomplete RESP frame in websocket message")),
+    }
+}
+
+impl<S> Sink<RespValue> for WsTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    type Error = anyhow::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<anyhow::Result<()>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_ready(cx)
+            .map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: RespValue) -> anyhow::Result<()> {
+        let mut buf = BytesMut::new();
+        RespCodec.encode(item, &mut buf)?;
+        Pin::new(&mut self.get_mut().inner).start_send(Message::Binary(buf.to_vec()))?;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<anyhow::Result<()>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_flush(cx)
+            .map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<anyhow::Result<()>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_close(cx)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::protocol::Role;
+
+    async fn pair() -> (
+        WebSocketStream<tokio::io::DuplexStream>,
+        WsTransport<tokio::io::DuplexStream>,
+    ) {
+        let (client, server) = tokio::io::duplex(4096);
+        let client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+        let server =
+            WsTransport::new(WebSocketStream::from_raw_socket(server, Role::Server, None).await);
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn test_decodes_binary_message_as_resp_value() {
+        let (mut client, mut server) = pair().await;
+        client
+            .send(Message::Binary(b"+PONG\r\n".to_vec()))
+            .await
+            .unwrap();
+        let value = server.next().await.unwrap().unwrap();
+        assert_eq!(value, RespValue::SimpleString("PONG".into()));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_text_frames() {
+        let (mut client, mut server) = pair().await;
+        client.send(Message::Text("hello".into())).await.unwrap();
+        assert!(server.next().await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ends_on_close_frame() {
+        let (mut client, mut server) = pair().await;
+        client.send(Message::Close(None)).await.unwrap();
+        assert!(server.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sink_encodes_resp_value_as_binary_message() {
+        let (mut client, mut server) = pair().await;
+        server
+            .send(RespValue::SimpleString("PONG".into()))
+            .await
+            .unwrap();
+        match client.next().await.unwrap().unwrap() {
+            Message::Binary(bytes) => assert_eq!(bytes, b"+PONG\r\n"),
+            other => panic!("expected a binary message, got {:?}", other),
+        }
+    }
+}